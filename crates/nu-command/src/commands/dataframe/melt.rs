@@ -0,0 +1,161 @@
+use crate::{
+    commands::dataframe::utils::{into_string_list, parse_polars_error},
+    prelude::*,
+};
+use nu_engine::WholeStreamCommand;
+use nu_errors::ShellError;
+use nu_protocol::{
+    dataframe::{NuDataFrame, PolarsData},
+    Signature, SyntaxShape, UntaggedValue, Value,
+};
+use nu_source::Tagged;
+
+use polars::prelude::DataFrame as PolarsDataFrame;
+
+pub struct DataFrame;
+
+impl WholeStreamCommand for DataFrame {
+    fn name(&self) -> &str {
+        "pls melt"
+    }
+
+    fn usage(&self) -> &str {
+        "Unpivots a dataframe from wide to long format"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build("pls melt")
+            .named(
+                "id-columns",
+                SyntaxShape::Table,
+                "columns to keep fixed for every row",
+                None,
+            )
+            .named(
+                "value-columns",
+                SyntaxShape::Table,
+                "columns to unpivot into the variable/value columns",
+                None,
+            )
+    }
+
+    fn run(&self, args: CommandArgs) -> Result<OutputStream, ShellError> {
+        command(args)
+    }
+
+    fn examples(&self) -> Vec<Example> {
+        vec![Example {
+            description: "Melt a dataframe to long format",
+            example:
+                "[[a b c]; [one 1 2] [two 3 4]] | pls convert | pls melt --id-columns [a] --value-columns [b c]",
+            result: None,
+        }]
+    }
+}
+
+fn command(args: CommandArgs) -> Result<OutputStream, ShellError> {
+    let tag = args.call_info.name_tag.clone();
+    let mut args = args.evaluate_once()?;
+
+    let id_cols = args
+        .get_flag::<Value>("id-columns")?
+        .ok_or_else(|| {
+            ShellError::labeled_error(
+                "Missing --id-columns",
+                "--id-columns requires at least one column name",
+                &tag,
+            )
+        })
+        .and_then(|value| into_string_list(value, "id-columns"))?;
+
+    let value_cols = args
+        .get_flag::<Value>("value-columns")?
+        .ok_or_else(|| {
+            ShellError::labeled_error(
+                "Missing --value-columns",
+                "--value-columns requires at least one column name",
+                &tag,
+            )
+        })
+        .and_then(|value| into_string_list(value, "value-columns"))?;
+
+    match args.input.next() {
+        None => Err(ShellError::labeled_error(
+            "No input received",
+            "missing dataframe input from stream",
+            &tag,
+        )),
+        Some(value) => {
+            if let UntaggedValue::DataFrame(PolarsData::EagerDataFrame(nu_df)) = value.value {
+                let df_ref = nu_df.as_ref();
+
+                for col in id_cols.iter().chain(value_cols.iter()) {
+                    check_column_exists(df_ref, col)?;
+                }
+
+                let id: Vec<String> = id_cols.iter().map(|c| c.item.clone()).collect();
+                let values: Vec<String> = value_cols.iter().map(|c| c.item.clone()).collect();
+
+                let res = df_ref
+                    .melt(&id, &values)
+                    .map_err(|e| parse_polars_error::<&str>(&e, &tag.span, None))?;
+
+                let final_df = Value {
+                    tag,
+                    value: UntaggedValue::DataFrame(PolarsData::EagerDataFrame(NuDataFrame::new(
+                        res,
+                    ))),
+                };
+
+                Ok(OutputStream::one(final_df))
+            } else {
+                Err(ShellError::labeled_error(
+                    "No dataframe in stream",
+                    "no dataframe found in input stream",
+                    &tag,
+                ))
+            }
+        }
+    }
+}
+
+fn check_column_exists(df: &PolarsDataFrame, col: &Tagged<String>) -> Result<(), ShellError> {
+    df.column(col.item.as_ref())
+        .map_err(|e| parse_polars_error::<&str>(&e, &col.tag.span, None))?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use polars::df;
+
+    #[test]
+    fn melt_produces_long_format() {
+        let df = df![
+            "a" => &["one", "two"],
+            "b" => &[1_i64, 3],
+            "c" => &[2_i64, 4],
+        ]
+        .unwrap();
+
+        let melted = df
+            .melt(&["a".to_string()], &["b".to_string(), "c".to_string()])
+            .unwrap();
+
+        assert_eq!(melted.height(), 4);
+        assert!(melted.column("variable").is_ok());
+        assert!(melted.column("value").is_ok());
+
+        let values: Vec<i64> = melted
+            .column("value")
+            .unwrap()
+            .i64()
+            .unwrap()
+            .into_iter()
+            .map(|v| v.unwrap())
+            .collect();
+        assert_eq!(values.iter().sum::<i64>(), 1 + 3 + 2 + 4);
+    }
+}
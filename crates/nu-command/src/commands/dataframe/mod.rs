@@ -0,0 +1,6 @@
+mod melt;
+mod pivot;
+mod utils;
+
+pub use melt::DataFrame as Melt;
+pub use pivot::DataFrame as Pivot;
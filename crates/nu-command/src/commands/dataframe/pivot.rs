@@ -1,4 +1,7 @@
-use crate::{commands::dataframe::utils::parse_polars_error, prelude::*};
+use crate::{
+    commands::dataframe::utils::{into_string_list, parse_polars_error},
+    prelude::*,
+};
 use nu_engine::WholeStreamCommand;
 use nu_errors::ShellError;
 use nu_protocol::{
@@ -7,7 +10,11 @@ use nu_protocol::{
 };
 use nu_source::Tagged;
 
-use polars::prelude::DataType;
+use std::collections::HashMap;
+
+use polars::prelude::{
+    AnyValue, DataFrame as PolarsDataFrame, DataType, GroupsIndicator, IdxSize, PolarsError, Series,
+};
 
 enum Operation {
     First,
@@ -16,6 +23,10 @@ enum Operation {
     Max,
     Mean,
     Median,
+    Count,
+    Last,
+    Std,
+    Var,
 }
 
 impl Operation {
@@ -27,15 +38,41 @@ impl Operation {
             "max" => Ok(Operation::Max),
             "mean" => Ok(Operation::Mean),
             "median" => Ok(Operation::Median),
+            "count" => Ok(Operation::Count),
+            "last" => Ok(Operation::Last),
+            "std" => Ok(Operation::Std),
+            "var" => Ok(Operation::Var),
             _ => Err(ShellError::labeled_error_with_secondary(
                 "Operation not fount",
                 "Operation does not exist for pivot",
                 &name.tag,
-                "Perhaps you want: first, sum, min, max, mean, median",
+                "Perhaps you want: first, sum, min, max, mean, median, count, last, std, var",
                 &name.tag,
             )),
         }
     }
+
+    // Runs the aggregation for this operation over `groupby`, returning one row per
+    // group in the same order as `groupby.get_groups()`.
+    fn aggregate(
+        &self,
+        groupby: &polars::prelude::GroupBy,
+        col: &str,
+    ) -> Result<PolarsDataFrame, PolarsError> {
+        let selected = groupby.select(col);
+        match self {
+            Operation::First => selected.first(),
+            Operation::Sum => selected.sum(),
+            Operation::Min => selected.min(),
+            Operation::Max => selected.max(),
+            Operation::Mean => selected.mean(),
+            Operation::Median => selected.median(),
+            Operation::Count => selected.count(),
+            Operation::Last => selected.last(),
+            Operation::Std => selected.std(),
+            Operation::Var => selected.var(),
+        }
+    }
 }
 
 pub struct DataFrame;
@@ -46,20 +83,33 @@ impl WholeStreamCommand for DataFrame {
     }
 
     fn usage(&self) -> &str {
-        "Performs a pivot operation on a groupby object"
+        "Widens a dataframe, aggregating value columns over one or more index columns"
     }
 
     fn signature(&self) -> Signature {
         Signature::build("pls pivot")
-            .required(
-                "pivot column",
-                SyntaxShape::String,
-                "pivot column to perform pivot",
+            .named(
+                "index",
+                SyntaxShape::Table,
+                "columns to keep fixed as the row index",
+                None,
             )
-            .required(
-                "value column",
+            .named(
+                "columns",
                 SyntaxShape::String,
-                "value column to perform pivot",
+                "column whose distinct values become the new columns",
+                None,
+            )
+            .named(
+                "values",
+                SyntaxShape::Table,
+                "value columns to aggregate",
+                None,
+            )
+            .switch(
+                "sort-columns",
+                "sort the output columns by pivot value instead of first-seen order",
+                None,
             )
             .required("operation", SyntaxShape::String, "aggregate operation")
     }
@@ -69,12 +119,32 @@ impl WholeStreamCommand for DataFrame {
     }
 
     fn examples(&self) -> Vec<Example> {
-        vec![Example {
-            description: "Pivot a dataframe on b and aggregation on col c",
-            example:
-                "[[a b c]; [one x 1] [two y 2]] | pls convert | pls groupby [a] | pls pivot b c sum",
-            result: None,
-        }]
+        vec![
+            Example {
+                description: "Pivot a dataframe on b and aggregation on col c",
+                example:
+                    "[[a b c]; [one x 1] [two y 2]] | pls convert | pls pivot --index [a] --columns b --values [c] sum",
+                result: None,
+            },
+            Example {
+                description: "Pivot a dataframe counting occurrences of each (a, b) pair",
+                example:
+                    "[[a b c]; [one x 1] [two y 2]] | pls convert | pls pivot --index [a] --columns b --values [c] count",
+                result: None,
+            },
+            Example {
+                description: "Pivot with several index and value columns at once",
+                example:
+                    "$df | pls pivot --index [a b] --columns region --values [sales profit] sum",
+                result: None,
+            },
+            Example {
+                description: "Pivot with output columns sorted by pivot value",
+                example:
+                    "[[a b c]; [one x 1] [two y 2]] | pls convert | pls pivot --index [a] --columns b --values [c] sum --sort-columns",
+                result: None,
+            },
+        ]
     }
 }
 
@@ -82,42 +152,70 @@ fn command(args: CommandArgs) -> Result<OutputStream, ShellError> {
     let tag = args.call_info.name_tag.clone();
     let mut args = args.evaluate_once()?;
 
-    // Extracting the pivot col from arguments
-    let pivot_col: Tagged<String> = args.req(0)?;
+    // Column whose distinct values become the output columns
+    let columns_col: Tagged<String> = args.get_flag("columns")?.ok_or_else(|| {
+        ShellError::labeled_error(
+            "Missing --columns",
+            "a --columns flag with the pivot column is required",
+            &tag,
+        )
+    })?;
 
-    // Extracting the value col from arguments
-    let value_col: Tagged<String> = args.req(1)?;
+    let index_cols = args
+        .get_flag::<Value>("index")?
+        .ok_or_else(|| {
+            ShellError::labeled_error(
+                "Missing --index",
+                "--index requires at least one column name",
+                &tag,
+            )
+        })
+        .and_then(|value| into_string_list(value, "index"))?;
+
+    let value_cols = args
+        .get_flag::<Value>("values")?
+        .ok_or_else(|| {
+            ShellError::labeled_error(
+                "Missing --values",
+                "--values requires at least one column name",
+                &tag,
+            )
+        })
+        .and_then(|value| into_string_list(value, "values"))?;
 
-    let operation: Tagged<String> = args.req(2)?;
+    let operation: Tagged<String> = args.req(0)?;
     let op = Operation::from_tagged(&operation)?;
 
-    // The operation is only done in one groupby. Only one input is
-    // expected from the InputStream
+    let sort_columns = args.has_flag("sort-columns");
+
     match args.input.next() {
         None => Err(ShellError::labeled_error(
             "No input received",
-            "missing groupby input from stream",
+            "missing dataframe input from stream",
             &tag,
         )),
         Some(value) => {
-            if let UntaggedValue::DataFrame(PolarsData::GroupBy(nu_groupby)) = value.value {
-                let df_ref = nu_groupby.as_ref();
+            if let UntaggedValue::DataFrame(PolarsData::EagerDataFrame(nu_df)) = value.value {
+                let df_ref = nu_df.as_ref();
 
-                check_pivot_column(df_ref, &pivot_col)?;
-                check_value_column(df_ref, &value_col)?;
+                check_pivot_column(df_ref, &columns_col)?;
 
-                let mut groupby = nu_groupby.to_groupby()?;
+                let allow_any = matches!(op, Operation::Count);
+                for value_col in &value_cols {
+                    check_value_column(df_ref, value_col, allow_any)?;
+                }
 
-                let pivot = groupby.pivot(pivot_col.item.as_ref(), value_col.item.as_ref());
+                let index: Vec<String> = index_cols.iter().map(|c| c.item.clone()).collect();
+                let values: Vec<String> = value_cols.iter().map(|c| c.item.clone()).collect();
 
-                let res = match op {
-                    Operation::Mean => pivot.mean(),
-                    Operation::Sum => pivot.sum(),
-                    Operation::Min => pivot.min(),
-                    Operation::Max => pivot.max(),
-                    Operation::First => pivot.first(),
-                    Operation::Median => pivot.median(),
-                }
+                let res = scatter_pivot(
+                    df_ref,
+                    &index,
+                    &columns_col.item,
+                    &values,
+                    &op,
+                    sort_columns,
+                )
                 .map_err(|e| parse_polars_error::<&str>(&e, &tag.span, None))?;
 
                 let final_df = Value {
@@ -130,8 +228,8 @@ fn command(args: CommandArgs) -> Result<OutputStream, ShellError> {
                 Ok(OutputStream::one(final_df))
             } else {
                 Err(ShellError::labeled_error(
-                    "No groupby in stream",
-                    "no groupby found in input stream",
+                    "No dataframe in stream",
+                    "no dataframe found in input stream",
                     &tag,
                 ))
             }
@@ -139,10 +237,135 @@ fn command(args: CommandArgs) -> Result<OutputStream, ShellError> {
     }
 }
 
-fn check_pivot_column(
-    df: &polars::prelude::DataFrame,
-    col: &Tagged<String>,
-) -> Result<(), ShellError> {
+// Widens `df` by scattering aggregated values into a row x column grid.
+//
+// Builds the (index columns, pivot column) groups once, assigns each distinct
+// pivot value and each distinct index combination an incrementing index via a
+// hashmap, aggregates every value column over the groups a single time, then
+// scatters each aggregated value directly into its (row, col) cell of a
+// per-column buffer.
+// This avoids the quadratic cost of filtering the frame once per distinct
+// pivot value.
+fn scatter_pivot(
+    df: &PolarsDataFrame,
+    index: &[String],
+    column: &str,
+    values: &[String],
+    op: &Operation,
+    sort_columns: bool,
+) -> Result<PolarsDataFrame, PolarsError> {
+    let mut group_cols = index.to_vec();
+    group_cols.push(column.to_string());
+
+    let groupby = df.groupby(&group_cols)?;
+    let groups = groupby.get_groups();
+
+    let pivot_series = df.column(column)?;
+    let index_series = index
+        .iter()
+        .map(|name| df.column(name))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut col_locations: Vec<IdxSize> = Vec::with_capacity(groups.len());
+    let mut row_locations: Vec<IdxSize> = Vec::with_capacity(groups.len());
+
+    let mut col_index: HashMap<String, IdxSize> = HashMap::new();
+    let mut col_names: Vec<String> = Vec::new();
+    let mut col_values: Vec<AnyValue> = Vec::new();
+
+    let mut row_index: HashMap<Vec<String>, IdxSize> = HashMap::new();
+    let mut row_representative: Vec<IdxSize> = Vec::new();
+
+    for group in groups.iter() {
+        let first = group_first(&group);
+
+        let pivot_any = pivot_series.get(first as usize);
+        let pivot_value = pivot_any.to_string();
+        let n_cols = col_names.len() as IdxSize;
+        let col_loc = *col_index.entry(pivot_value.clone()).or_insert_with(|| {
+            col_names.push(pivot_value);
+            col_values.push(pivot_any);
+            n_cols
+        });
+        col_locations.push(col_loc);
+
+        let row_key: Vec<String> = index_series
+            .iter()
+            .map(|s| s.get(first as usize).to_string())
+            .collect();
+        let n_rows = row_representative.len() as IdxSize;
+        let row_loc = *row_index.entry(row_key).or_insert_with(|| {
+            row_representative.push(first);
+            n_rows
+        });
+        row_locations.push(row_loc);
+    }
+
+    let n_rows = row_representative.len();
+    let n_cols = col_names.len();
+
+    // pairs of (column name, its scatter location), in the order columns
+    // should be assembled into the output frame
+    let mut column_order: Vec<(String, usize)> = col_names
+        .into_iter()
+        .enumerate()
+        .map(|(loc, name)| (name, loc))
+        .collect();
+    if sort_columns {
+        // Compare the original pivot values rather than their string form, so
+        // numeric pivot columns (e.g. 1, 2, 10) sort numerically instead of
+        // lexicographically.
+        column_order.sort_by(|a, b| {
+            col_values[a.1]
+                .partial_cmp(&col_values[b.1])
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+    }
+
+    // the index columns, one value per distinct row
+    let mut out_columns: Vec<Series> = index_series
+        .iter()
+        .map(|s| s.take_iter(&mut row_representative.iter().map(|idx| *idx as usize)))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for value_col in values {
+        let agg_df = op.aggregate(&groupby, value_col)?;
+        // the aggregation always appends its result as the last column
+        let agg_series = agg_df.get_columns()[agg_df.width() - 1].clone();
+
+        // One null-filled Vec<AnyValue> per output column; each group is
+        // scattered into its (row, col) slot in a single O(1) write, so the
+        // whole pass is O(n_groups) rather than rebuilding an n_rows-long
+        // array per group.
+        let mut buffer: Vec<Vec<AnyValue>> = vec![vec![AnyValue::Null; n_rows]; n_cols];
+
+        for (i, (&row, &col)) in row_locations.iter().zip(col_locations.iter()).enumerate() {
+            buffer[col as usize][row as usize] = agg_series.get(i);
+        }
+
+        for (col_name, col_loc) in &column_order {
+            let name = if values.len() > 1 {
+                format!("{}_{}", value_col, col_name)
+            } else {
+                col_name.clone()
+            };
+
+            let series = Series::from_any_values(&name, &buffer[*col_loc])?;
+            out_columns.push(series);
+        }
+    }
+
+    PolarsDataFrame::new(out_columns)
+}
+
+fn group_first(group: &GroupsIndicator) -> IdxSize {
+    match group {
+        GroupsIndicator::Idx((first, _)) => *first,
+        GroupsIndicator::Slice([first, _]) => *first,
+    }
+}
+
+fn check_pivot_column(df: &PolarsDataFrame, col: &Tagged<String>) -> Result<(), ShellError> {
     let series = df
         .column(col.item.as_ref())
         .map_err(|e| parse_polars_error::<&str>(&e, &col.tag.span, None))?;
@@ -166,13 +389,19 @@ fn check_pivot_column(
 }
 
 fn check_value_column(
-    df: &polars::prelude::DataFrame,
+    df: &PolarsDataFrame,
     col: &Tagged<String>,
+    allow_any: bool,
 ) -> Result<(), ShellError> {
     let series = df
         .column(col.item.as_ref())
         .map_err(|e| parse_polars_error::<&str>(&e, &col.tag.span, None))?;
 
+    // Counting occurrences does not require an arithmetic dtype
+    if allow_any {
+        return Ok(());
+    }
+
     match series.dtype() {
         DataType::UInt8
         | DataType::UInt16
@@ -191,3 +420,128 @@ fn check_value_column(
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_source::Tag;
+    use polars::df;
+
+    fn tagged(name: &str) -> Tagged<String> {
+        Tagged {
+            item: name.to_string(),
+            tag: Tag::unknown(),
+        }
+    }
+
+    #[test]
+    fn parses_new_operations() {
+        for name in ["count", "last", "std", "var"] {
+            assert!(Operation::from_tagged(&tagged(name)).is_ok());
+        }
+    }
+
+    #[test]
+    fn count_allows_non_numeric_value_column() {
+        let df = df![
+            "a" => &["one", "one", "two"],
+            "flag" => &[true, false, true],
+        ]
+        .unwrap();
+
+        assert!(check_value_column(&df, &tagged("flag"), true).is_ok());
+        assert!(check_value_column(&df, &tagged("flag"), false).is_err());
+    }
+
+    #[test]
+    fn scatter_pivot_with_multiple_index_and_value_columns() {
+        let df = df![
+            "region" => &["east", "east", "west", "west"],
+            "year" => &[2020, 2021, 2020, 2021],
+            "quarter" => &["q1", "q1", "q1", "q1"],
+            "sales" => &[10_i64, 20, 30, 40],
+            "profit" => &[1_i64, 2, 3, 4],
+        ]
+        .unwrap();
+
+        let result = scatter_pivot(
+            &df,
+            &["region".to_string(), "year".to_string()],
+            "quarter",
+            &["sales".to_string(), "profit".to_string()],
+            &Operation::Sum,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(result.height(), 4);
+        assert!(result.column("sales_q1").is_ok());
+        assert!(result.column("profit_q1").is_ok());
+    }
+
+    #[test]
+    fn scatter_pivot_places_values_in_correct_cells_only() {
+        let df = df![
+            "a" => &["one", "two"],
+            "b" => &["x", "y"],
+            "c" => &[1_i64, 2],
+        ]
+        .unwrap();
+
+        let result = scatter_pivot(
+            &df,
+            &["a".to_string()],
+            "b",
+            &["c".to_string()],
+            &Operation::Sum,
+            true,
+        )
+        .unwrap();
+
+        let one_row = result
+            .column("a")
+            .unwrap()
+            .utf8()
+            .unwrap()
+            .into_iter()
+            .position(|v| v == Some("one"))
+            .unwrap();
+        let two_row = 1 - one_row;
+
+        let x_col = result.column("x").unwrap();
+        let y_col = result.column("y").unwrap();
+
+        assert_eq!(x_col.get(one_row), AnyValue::Int64(1));
+        assert_eq!(x_col.get(two_row), AnyValue::Null);
+        assert_eq!(y_col.get(two_row), AnyValue::Int64(2));
+        assert_eq!(y_col.get(one_row), AnyValue::Null);
+    }
+
+    #[test]
+    fn sort_columns_orders_numeric_pivot_values_numerically() {
+        let df = df![
+            "a" => &["row", "row", "row"],
+            "b" => &[10_i64, 2, 1],
+            "c" => &[100_i64, 200, 300],
+        ]
+        .unwrap();
+
+        let result = scatter_pivot(
+            &df,
+            &["a".to_string()],
+            "b",
+            &["c".to_string()],
+            &Operation::Sum,
+            true,
+        )
+        .unwrap();
+
+        let names: Vec<&str> = result
+            .get_column_names()
+            .into_iter()
+            .filter(|n| *n != "a")
+            .collect();
+
+        assert_eq!(names, vec!["1", "2", "10"]);
+    }
+}
@@ -0,0 +1,30 @@
+use nu_errors::ShellError;
+use nu_protocol::{Primitive, UntaggedValue, Value};
+use nu_source::Tagged;
+
+// Pulls a list of column names out of a table-shaped list flag, e.g. the
+// `--index`/`--values` flags on `pls pivot` or `--id-columns`/`--value-columns`
+// on `pls melt`.
+pub fn into_string_list(value: Value, flag: &str) -> Result<Vec<Tagged<String>>, ShellError> {
+    match value.value {
+        UntaggedValue::Table(columns) => columns
+            .into_iter()
+            .map(|column| match &column.value {
+                UntaggedValue::Primitive(Primitive::String(s)) => Ok(Tagged {
+                    item: s.clone(),
+                    tag: column.tag.clone(),
+                }),
+                _ => Err(ShellError::labeled_error(
+                    format!("Unsupported value in --{}", flag),
+                    "expected a string column name",
+                    &column.tag,
+                )),
+            })
+            .collect(),
+        _ => Err(ShellError::labeled_error(
+            format!("--{} expects a list of column names", flag),
+            "expected a list",
+            &value.tag,
+        )),
+    }
+}